@@ -0,0 +1,24 @@
+use keyring::Entry;
+
+use crate::Config;
+
+const SERVICE: &str = "buaa-iclass-cli";
+
+/// Save `password` into the platform secret store, keyed by `username`.
+pub(crate) fn save_password(username: &str, password: &str) -> keyring::Result<()> {
+    Entry::new(SERVICE, username)?.set_password(password)
+}
+
+/// Load the password saved for `username` from the platform secret store, if any.
+pub(crate) fn load_password(username: &str) -> Option<String> {
+    Entry::new(SERVICE, username).ok()?.get_password().ok()
+}
+
+/// Resolve the password to log in with: prefer the plaintext `Config` field for
+/// backward compatibility with existing configs, falling back to the keyring entry.
+pub(crate) fn resolve_password(config: &Config) -> String {
+    if !config.password.is_empty() {
+        return config.password.clone();
+    }
+    load_password(&config.username).unwrap_or_default()
+}
@@ -0,0 +1,148 @@
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+use buaa_api::Session;
+use time::{Duration as TimeDuration, PrimitiveDateTime, Time};
+use tokio::time::Duration;
+
+use crate::history::{History, HistoryEntry};
+use crate::notify::{self, NotifyConfig};
+use crate::poll::{schedule_state, ScheduleState};
+use crate::{get_primitive_time, parse_hhmm, CheckinJob, HISTORY_PATH};
+
+/// Compute the next `PrimitiveDateTime` strictly after `now` at which `time` falls on a
+/// weekday enabled in `mask` (bit 0 = Monday ... bit 6 = Sunday).
+fn next_occurrence(now: PrimitiveDateTime, mask: u8, time: Time) -> PrimitiveDateTime {
+    for offset in 0..=7 {
+        let date = now.date() + TimeDuration::days(offset);
+        let bit = 1u8 << (date.weekday().number_from_monday() - 1);
+        if mask & bit == 0 {
+            continue;
+        }
+        let candidate = PrimitiveDateTime::new(date, time);
+        if candidate > now {
+            return candidate;
+        }
+    }
+    // Mask had no enabled day within a full week, which should not happen for a real job.
+    // Fall back to the same slot next week so the daemon keeps making progress.
+    PrimitiveDateTime::new(now.date() + TimeDuration::days(7), time)
+}
+
+/// Run the daemon: keep a min-heap of the next fire time per job, sleep until the
+/// earliest one, check in, then reschedule that job for its next weekly occurrence.
+pub(crate) async fn run(
+    session: &mut Session,
+    user_id: &str,
+    jobs: &[CheckinJob],
+    notify_config: &Option<NotifyConfig>,
+    history: &mut History,
+) {
+    let mut heap: BinaryHeap<Reverse<(PrimitiveDateTime, usize)>> = BinaryHeap::new();
+    for (index, job) in jobs.iter().enumerate() {
+        let Some(time) = parse_hhmm(&job.time) else {
+            eprintln!("[Info]: Job for course {} has malformed time '{}', skipping", job.course, job.time);
+            continue;
+        };
+        let fire = next_occurrence(get_primitive_time(), job.weekday_mask, time);
+        heap.push(Reverse((fire, index)));
+    }
+    println!("[Info]: Daemon armed with {} job(s)", jobs.len());
+
+    loop {
+        let Reverse((fire, index)) = match heap.pop() {
+            Some(entry) => entry,
+            None => break,
+        };
+        let job = &jobs[index];
+
+        let wait = fire - get_primitive_time();
+        if wait.whole_seconds() > 0 {
+            tokio::time::sleep(Duration::from_secs(wait.whole_seconds() as u64)).await;
+        }
+        let fire_time = get_primitive_time();
+
+        match session.iclass_query_schedule(&job.course, user_id).await {
+            Ok(schedule) => match schedule.iter().find(|s| schedule_state(s) == ScheduleState::Open) {
+                Some(schedule) => {
+                    let success = match session.iclass_checkin_schedule(&schedule.id, user_id).await {
+                        Ok(_) => {
+                            println!("[Info]: Checkin successfully for course {}", job.course);
+                            notify::notify(notify_config, "IClass checkin succeeded", &format!(
+                                "Checked in to course {} at {}", job.course, fire_time
+                            )).await;
+                            true
+                        },
+                        Err(e) => {
+                            eprintln!("[Info]: Checkin failed for course {}: {:?}", job.course, e);
+                            notify::notify(notify_config, "IClass checkin failed", &format!(
+                                "Checkin to course {} failed at {}: {:?}", job.course, fire_time, e
+                            )).await;
+                            false
+                        },
+                    };
+                    history.record(HistoryEntry {
+                        course: Some(job.course.clone()),
+                        schedule: schedule.id.clone(),
+                        target_time: Some(fire.to_string()),
+                        fire_time: fire_time.to_string(),
+                        success,
+                    });
+                    history.save(HISTORY_PATH);
+                },
+                None => eprintln!(
+                    "[Info]: No open schedule for course {} at fire time, will retry next occurrence",
+                    job.course
+                ),
+            },
+            Err(e) => eprintln!("[Info]: Query schedule failed for course {}: {:?}", job.course, e),
+        }
+
+        let Some(time) = parse_hhmm(&job.time) else {
+            eprintln!("[Info]: Job for course {} has malformed time '{}', dropping from schedule", job.course, job.time);
+            continue;
+        };
+        let next = next_occurrence(get_primitive_time().max(fire), job.weekday_mask, time);
+        heap.push(Reverse((next, index)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use time::Month;
+
+    use super::*;
+
+    // 2024-03-04 is a Monday.
+    fn monday_at(hour: u8, minute: u8) -> PrimitiveDateTime {
+        let date = time::Date::from_calendar_date(2024, Month::March, 4).unwrap();
+        PrimitiveDateTime::new(date, Time::from_hms(hour, minute, 0).unwrap())
+    }
+
+    #[test]
+    fn next_occurrence_rolls_to_next_week_when_only_today_is_enabled_and_time_has_passed() {
+        let now = monday_at(9, 0);
+        let target = Time::from_hms(8, 0, 0).unwrap();
+        let next = next_occurrence(now, 0b000_0001, target);
+        assert_eq!(next.date(), now.date() + TimeDuration::days(7));
+        assert_eq!(next.time(), target);
+    }
+
+    #[test]
+    fn next_occurrence_fires_later_today_when_time_has_not_passed() {
+        let now = monday_at(7, 0);
+        let target = Time::from_hms(8, 0, 0).unwrap();
+        let next = next_occurrence(now, 0b000_0001, target);
+        assert_eq!(next.date(), now.date());
+        assert_eq!(next.time(), target);
+    }
+
+    #[test]
+    fn next_occurrence_falls_back_a_week_when_mask_has_no_enabled_day() {
+        let now = monday_at(9, 0);
+        let target = Time::from_hms(8, 0, 0).unwrap();
+        let next = next_occurrence(now, 0, target);
+        assert_eq!(next.date(), now.date() + TimeDuration::days(7));
+        assert_eq!(next.time(), target);
+    }
+}
@@ -0,0 +1,96 @@
+use std::fs::OpenOptions;
+
+use serde::{Deserialize, Serialize};
+use tabled::Tabled;
+use time::{Date, Month};
+
+use crate::load_or_refuse_corrupt;
+
+/// One recorded check-in attempt. `course`/`target_time` are `None` when the attempt
+/// was made directly against a schedule ID (`checkin --schedule`) and the owning
+/// course/target time were never known, rather than an empty string standing in for
+/// "unknown" and accidentally matching `log --course ""`.
+#[derive(Debug, Clone, Deserialize, Serialize, Tabled)]
+pub(crate) struct HistoryEntry {
+    #[tabled(display_with = "display_option")]
+    pub(crate) course: Option<String>,
+    pub(crate) schedule: String,
+    #[tabled(display_with = "display_option")]
+    pub(crate) target_time: Option<String>,
+    pub(crate) fire_time: String,
+    pub(crate) success: bool,
+}
+
+fn display_option(value: &Option<String>) -> String {
+    value.clone().unwrap_or_else(|| "-".to_string())
+}
+
+/// Append-only check-in attendance history, persisted next to the config/cookie files.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub(crate) struct History {
+    entries: Vec<HistoryEntry>,
+}
+
+impl History {
+    pub(crate) fn load(path: &str) -> Self {
+        load_or_refuse_corrupt(path)
+    }
+
+    pub(crate) fn save(&self, path: &str) {
+        let file = OpenOptions::new().write(true).truncate(true).create(true).open(path).unwrap();
+        serde_json::to_writer(file, self).unwrap();
+    }
+
+    pub(crate) fn record(&mut self, entry: HistoryEntry) {
+        self.entries.push(entry);
+    }
+
+    /// Render the history as a table, optionally filtered by course ID and/or by the
+    /// `[from, to]` date range the attempt actually fired in.
+    pub(crate) fn table(&self, course: Option<&str>, from: Option<Date>, to: Option<Date>) -> String {
+        let filtered: Vec<HistoryEntry> = self
+            .entries
+            .iter()
+            .filter(|entry| course.map_or(true, |c| entry.course.as_deref() == Some(c)))
+            .filter(|entry| {
+                let Some(date) = entry_date(&entry.fire_time) else {
+                    return true;
+                };
+                from.map_or(true, |from| date >= from) && to.map_or(true, |to| date <= to)
+            })
+            .cloned()
+            .collect();
+        buaa_api::utils::table(&filtered)
+    }
+}
+
+/// Pull the `YYYY-MM-DD` date out of a `PrimitiveDateTime`'s `Display` output, as
+/// stored in `target_time`/`fire_time`.
+fn entry_date(timestamp: &str) -> Option<Date> {
+    let mut parts = timestamp.split_whitespace().next()?.splitn(3, '-');
+    let year = parts.next()?.parse::<i32>().ok()?;
+    let month = parts.next()?.parse::<u8>().ok()?;
+    let day = parts.next()?.parse::<u8>().ok()?;
+    Date::from_calendar_date(year, Month::try_from(month).ok()?, day).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn entry_date_parses_display_format() {
+        let date = entry_date("2024-03-05 8:00:00.0").unwrap();
+        assert_eq!(date, Date::from_calendar_date(2024, Month::March, 5).unwrap());
+    }
+
+    #[test]
+    fn entry_date_rejects_invalid_month() {
+        assert_eq!(entry_date("2024-13-05 8:00:00.0"), None);
+    }
+
+    #[test]
+    fn entry_date_rejects_garbage() {
+        assert_eq!(entry_date("not-a-timestamp"), None);
+    }
+}
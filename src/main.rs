@@ -1,10 +1,24 @@
 use buaa_api::{Session, IClassCourse};
 use clap::{Parser, Subcommand};
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 use time::{OffsetDateTime, PrimitiveDateTime, Time, UtcOffset};
 use tokio::time::Duration;
 
-use std::fs::{File, OpenOptions};
+use std::fs::OpenOptions;
+
+mod credential;
+mod daemon;
+mod history;
+mod notify;
+mod poll;
+mod timetable;
+
+use history::{History, HistoryEntry};
+use notify::NotifyConfig;
+use timetable::UntisCredentials;
+
+const HISTORY_PATH: &str = "buaa-iclass-history.json";
 
 #[derive(Debug, Parser)]
 #[command(
@@ -54,7 +68,64 @@ enum Commands {
         #[arg(short, long)]
         /// eg. '0800' means 8:00.
         time: Option<String>,
-    }
+        #[arg(long)]
+        /// Once the target time arrives, keep polling the schedule every N seconds
+        /// until it is open for check-in, instead of checking in on the first query.
+        poll: Option<u64>,
+        #[arg(long, default_value_t = 5)]
+        /// How many minutes to keep polling for after the target time, when `--poll` is set.
+        window: u64,
+    },
+    /// Run recurring check-ins for every job configured via the `jobs` list,
+    /// keeping the process alive for the whole semester.
+    Daemon,
+    /// Show the currently configured account without exposing the password.
+    Show,
+    /// Load the saved credential and perform a single named action, for scripting / cron use.
+    Exec {
+        /// Name of the action to run, eg. 'login'.
+        action: String,
+    },
+    /// Manage recurring check-in jobs derived from a term timetable.
+    Schedule {
+        #[command(subcommand)]
+        action: ScheduleAction,
+    },
+    /// Review past check-in attempts.
+    Log {
+        #[arg(short, long)]
+        /// Only show attempts for this course ID.
+        course: Option<String>,
+        /// Only show attempts on or after this date, as 'YYYYMMDD'.
+        #[arg(long)]
+        from: Option<i32>,
+        /// Only show attempts on or before this date, as 'YYYYMMDD'.
+        #[arg(long)]
+        to: Option<i32>,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum ScheduleAction {
+    /// Import a term timetable from an external WebUntis-like source and turn it
+    /// into recurring check-in jobs matched against the saved IClass courses.
+    Import {
+        /// Base URL of the WebUntis-like server, eg. 'https://example.webuntis.com'.
+        #[arg(long)]
+        untis_url: String,
+        #[arg(long)]
+        untis_school: String,
+        #[arg(long)]
+        untis_username: String,
+        #[arg(long)]
+        untis_password: String,
+        /// First day of the term to import, as 'YYYYMMDD'.
+        #[arg(long)]
+        start: i32,
+        /// Last day of the term to import, as 'YYYYMMDD'.
+        #[arg(long)]
+        end: i32,
+    },
 }
 
 #[derive(Debug, Default, Deserialize, Serialize)]
@@ -63,20 +134,51 @@ struct Config {
     password: String,
     user_id: String,
     courses: Vec<IClassCourse>,
+    /// Recurring check-in jobs run by `Commands::Daemon`.
+    #[serde(default)]
+    jobs: Vec<CheckinJob>,
+    /// SMTP settings for emailing check-in results. Absent by default (no-op).
+    notify: Option<NotifyConfig>,
 }
 
-fn main() {
-    let file = OpenOptions::new()
-        .read(true)
-        .write(true)
-        .create(true)
-        .open("buaa-iclass-config.json")
-        .unwrap();
-    let mut config = match serde_json::from_reader::<File, Config>(file){
-        Ok(config) => config,
-        Err(_) => Config::default(),
+/// A recurring check-in job: check in to `course` whenever the current weekday is
+/// enabled in `weekday_mask` (bit 0 = Monday ... bit 6 = Sunday) at `time`.
+#[derive(Debug, Default, Clone, Deserialize, Serialize)]
+pub(crate) struct CheckinJob {
+    course: String,
+    weekday_mask: u8,
+    /// eg. '0800' means 8:00.
+    time: String,
+}
+
+/// Open `path` (creating it if missing) and parse it as JSON. An empty file means
+/// this is a first run and yields `T::default()`; anything else that fails to parse
+/// is treated as a corrupt file that we should not silently overwrite on save.
+pub(crate) fn load_or_refuse_corrupt<T: Default + DeserializeOwned>(path: &str) -> T {
+    let file = match OpenOptions::new().read(true).write(true).create(true).open(path) {
+        Ok(file) => file,
+        Err(_) => return T::default(),
+    };
+    let contents = match std::io::read_to_string(file) {
+        Ok(contents) => contents,
+        Err(_) => return T::default(),
     };
+    if contents.trim().is_empty() {
+        return T::default();
+    }
+    match serde_json::from_str(&contents) {
+        Ok(value) => value,
+        Err(e) => {
+            eprintln!("[Info]: Failed to parse {path}, refusing to overwrite it: {:?}", e);
+            std::process::exit(1);
+        },
+    }
+}
+
+fn main() {
+    let mut config: Config = load_or_refuse_corrupt("buaa-iclass-config.json");
     let mut session = Session::new_in_file("buaa-iclass-cookie.json");
+    let mut history = History::load(HISTORY_PATH);
     let runtime = tokio::runtime::Builder::new_current_thread()
         .enable_all()
         .build()
@@ -90,10 +192,14 @@ fn main() {
             if let Some(password) = password {
                 config.password = password;
             }
-            runtime.block_on(async {
-                match session.sso_login(&config.username, &config.password).await {
+            let login_succeeded = runtime.block_on(async {
+                let login_password = credential::resolve_password(&config);
+                match session.sso_login(&config.username, &login_password).await {
                     Ok(_) => println!("[Info]: SSO Login successfully"),
-                    Err(e) => eprintln!("[Info]: SSO Login failed: {:?}", e),
+                    Err(e) => {
+                        eprintln!("[Info]: SSO Login failed: {:?}", e);
+                        return false;
+                    },
                 }
                 let id = match session.iclass_login().await {
                     Ok(s) => {
@@ -102,11 +208,24 @@ fn main() {
                     },
                     Err(e) => {
                         eprintln!("[Info]: Iclass Login failed: {:?}", e);
-                        return;
+                        return false;
                     },
                 };
                 config.user_id = id;
+                true
             });
+            // Only move a freshly-provided plaintext password into the keyring once we've
+            // confirmed it actually works, so a mistyped/expired password doesn't silently
+            // overwrite a previously-good saved one.
+            if login_succeeded && !config.password.is_empty() {
+                match credential::save_password(&config.username, &config.password) {
+                    Ok(_) => {
+                        println!("[Info]: Password saved to system keyring");
+                        config.password.clear();
+                    },
+                    Err(e) => eprintln!("[Info]: Failed to save password to keyring, keeping plaintext fallback: {:?}", e),
+                }
+            }
         },
         Commands::List { remove } => {
             if let Some(id) = remove {
@@ -145,47 +264,177 @@ fn main() {
                 });
             }
         },
-        Commands::Checkin { schedule, course, time } => {
+        Commands::Checkin { schedule, course, time, poll, window } => {
             if let Some(schedule) = schedule {
                 runtime.block_on(async {
-                    match session.iclass_checkin_schedule(&schedule, &config.user_id).await {
-                        Ok(_) => println!("[Info]: Checkin successfully"),
-                        Err(e) => eprintln!("[Info]: Checkin failed: {:?}", e),
-                    }
+                    let fire_time = get_primitive_time();
+                    let success = match session.iclass_checkin_schedule(&schedule, &config.user_id).await {
+                        Ok(_) => {
+                            println!("[Info]: Checkin successfully");
+                            notify::notify(&config.notify, "IClass checkin succeeded", &format!(
+                                "Checked in to schedule {} at {}", schedule, fire_time
+                            )).await;
+                            true
+                        },
+                        Err(e) => {
+                            eprintln!("[Info]: Checkin failed: {:?}", e);
+                            notify::notify(&config.notify, "IClass checkin failed", &format!(
+                                "Checkin to schedule {} failed at {}: {:?}", schedule, fire_time, e
+                            )).await;
+                            false
+                        },
+                    };
+                    history.record(HistoryEntry {
+                        course: None,
+                        schedule: schedule.clone(),
+                        target_time: None,
+                        fire_time: fire_time.to_string(),
+                        success,
+                    });
                 });
             }
             if let Some(course) = course {
                 if let Some(time) = time {
-                    let hour = time[0..2].parse::<u8>().unwrap();
-                    let minute = time[2..4].parse::<u8>().unwrap();
-                    let time = Time::from_hms(hour, minute, 0).unwrap();
-                    let now = get_primitive_time();
-                    let target = PrimitiveDateTime::new(now.date(), time);
-                    let duration = target - now;
-                    let second = duration.whole_seconds() + 5;
-                    // 如果时间大于零那么就等待
-                    if second > 0 {
-                        let duration = Duration::from_secs(second as u64);
-                        println!("[Info]: Waiting for {} seconds", second);
-                        runtime.block_on(async {
-                            tokio::time::sleep(duration).await;
-                            let schedule = match session.iclass_query_schedule(&course, &config.user_id).await {
-                                Ok(schedule) => schedule,
-                                Err(e) => {
-                                    eprintln!("[Info]: Query schedule failed: {:?}", e);
-                                    return;
-                                },
-                            };
-                            let schedule = schedule.last().unwrap();
-                            match session.iclass_checkin_schedule(&schedule.id, &config.user_id).await {
-                                Ok(_) => println!("[Info]: Checkin successfully"),
-                                Err(e) => eprintln!("[Info]: Checkin failed: {:?}", e),
+                    match parse_hhmm(&time) {
+                        Some(time) => {
+                            let now = get_primitive_time();
+                            let target = PrimitiveDateTime::new(now.date(), time);
+                            let duration = target - now;
+                            let second = duration.whole_seconds() + 5;
+                            // 如果时间大于零那么就等待
+                            if second > 0 {
+                                let duration = Duration::from_secs(second as u64);
+                                println!("[Info]: Waiting for {} seconds", second);
+                                runtime.block_on(async {
+                                    tokio::time::sleep(duration).await;
+                                    match poll {
+                                        Some(poll_interval) => {
+                                            poll::poll_until_open(
+                                                &mut session, &course, &config.user_id, poll_interval, window,
+                                                &config.notify, &mut history, &target.to_string(),
+                                            ).await;
+                                        },
+                                        None => {
+                                            let fire_time = get_primitive_time();
+                                            let schedule = match session.iclass_query_schedule(&course, &config.user_id).await {
+                                                Ok(schedule) => schedule,
+                                                Err(e) => {
+                                                    eprintln!("[Info]: Query schedule failed: {:?}", e);
+                                                    return;
+                                                },
+                                            };
+                                            let schedule = match schedule.last() {
+                                                Some(schedule) => schedule,
+                                                None => {
+                                                    eprintln!("[Info]: No schedule for course {} at fire time, giving up", course);
+                                                    return;
+                                                },
+                                            };
+                                            let success = match session.iclass_checkin_schedule(&schedule.id, &config.user_id).await {
+                                                Ok(_) => {
+                                                    println!("[Info]: Checkin successfully");
+                                                    notify::notify(&config.notify, "IClass checkin succeeded", &format!(
+                                                        "Checked in to course {} at {}", course, fire_time
+                                                    )).await;
+                                                    true
+                                                },
+                                                Err(e) => {
+                                                    eprintln!("[Info]: Checkin failed: {:?}", e);
+                                                    notify::notify(&config.notify, "IClass checkin failed", &format!(
+                                                        "Checkin to course {} failed at {}: {:?}", course, fire_time, e
+                                                    )).await;
+                                                    false
+                                                },
+                                            };
+                                            history.record(HistoryEntry {
+                                                course: Some(course.clone()),
+                                                schedule: schedule.id.clone(),
+                                                target_time: Some(target.to_string()),
+                                                fire_time: fire_time.to_string(),
+                                                success,
+                                            });
+                                        },
+                                    }
+                                })
                             }
-                        })
+                        },
+                        None => eprintln!("[Info]: Malformed --time value '{}', expected HHMM", time),
                     }
                 }
             }
-        }
+        },
+        Commands::Daemon => {
+            if config.jobs.is_empty() {
+                println!("[Info]: No check-in jobs configured, nothing to run");
+            } else {
+                let daemon_runtime = tokio::runtime::Builder::new_multi_thread()
+                    .enable_all()
+                    .build()
+                    .unwrap();
+                daemon_runtime.block_on(daemon::run(&mut session, &config.user_id, &config.jobs, &config.notify, &mut history));
+            }
+        },
+        Commands::Show => {
+            let password = if !config.password.is_empty() {
+                "<plaintext in config, re-login to move it to the keyring>"
+            } else if credential::load_password(&config.username).is_some() {
+                "<stored in keyring>"
+            } else {
+                "<not set>"
+            };
+            println!("Username: {}", config.username);
+            println!("User ID: {}", config.user_id);
+            println!("Password: {}", password);
+            println!("Saved courses: {}", config.courses.len());
+            println!("Scheduled jobs: {}", config.jobs.len());
+        },
+        Commands::Exec { action } => match action.as_str() {
+            "login" => {
+                runtime.block_on(async {
+                    let login_password = credential::resolve_password(&config);
+                    match session.sso_login(&config.username, &login_password).await {
+                        Ok(_) => println!("[Info]: SSO Login successfully"),
+                        Err(e) => {
+                            eprintln!("[Info]: SSO Login failed: {:?}", e);
+                            return;
+                        },
+                    }
+                    match session.iclass_login().await {
+                        Ok(id) => {
+                            println!("[Info]: Iclass Login successfully");
+                            config.user_id = id;
+                        },
+                        Err(e) => eprintln!("[Info]: Iclass Login failed: {:?}", e),
+                    }
+                });
+            },
+            other => eprintln!("[Info]: Unknown exec action '{}'", other),
+        },
+        Commands::Schedule { action } => match action {
+            ScheduleAction::Import { untis_url, untis_school, untis_username, untis_password, start, end } => {
+                let creds = UntisCredentials {
+                    base_url: untis_url,
+                    school: untis_school,
+                    username: untis_username,
+                    password: untis_password,
+                };
+                runtime.block_on(async {
+                    match timetable::import_jobs(&creds, start, end, &config.courses).await {
+                        Ok(jobs) => {
+                            println!("[Info]: Imported {} check-in job(s)", jobs.len());
+                            config.jobs = jobs;
+                        },
+                        Err(e) => eprintln!("[Info]: Timetable import failed: {}", e),
+                    }
+                });
+            },
+        },
+        Commands::Log { course, from, to } => {
+            let from = from.and_then(timetable::decode_date);
+            let to = to.and_then(timetable::decode_date);
+            let table = history.table(course.as_deref(), from, to);
+            println!("{}", table);
+        },
     }
     session.save();
     let file = OpenOptions::new()
@@ -194,11 +443,23 @@ fn main() {
         .open("buaa-iclass-config.json")
         .unwrap();
     serde_json::to_writer(file, &config).unwrap();
+    history.save(HISTORY_PATH);
 }
 
-fn get_primitive_time() -> PrimitiveDateTime {
+pub(crate) fn get_primitive_time() -> PrimitiveDateTime {
     let now_utc = OffsetDateTime::now_utc();
     let local_offset = UtcOffset::from_hms(8, 0, 0).unwrap();
     let now_local = now_utc.to_offset(local_offset);
     PrimitiveDateTime::new(now_local.date(), now_local.time())
+}
+
+/// Parse an `HHMM` string, eg. '0800' means 8:00. Returns `None` if `time` is not
+/// exactly 4 digits or does not name a valid hour/minute.
+pub(crate) fn parse_hhmm(time: &str) -> Option<Time> {
+    if time.len() != 4 {
+        return None;
+    }
+    let hour = time[0..2].parse::<u8>().ok()?;
+    let minute = time[2..4].parse::<u8>().ok()?;
+    Time::from_hms(hour, minute, 0).ok()
 }
\ No newline at end of file
@@ -0,0 +1,51 @@
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{Message, SmtpTransport, Transport};
+use serde::{Deserialize, Serialize};
+
+/// SMTP settings used to email the student a summary of each check-in attempt.
+/// Absent from `Config` by default, in which case `notify` is a no-op.
+#[derive(Debug, Default, Clone, Deserialize, Serialize)]
+pub(crate) struct NotifyConfig {
+    host: String,
+    port: u16,
+    username: String,
+    password: String,
+    to: String,
+}
+
+/// Email `subject`/`body` to the configured address. Does nothing if `config` is `None`.
+///
+/// `lettre`'s `SmtpTransport` is blocking, so the actual send happens on a blocking
+/// thread pool via `spawn_blocking` instead of stalling the calling task's executor
+/// thread for the full SMTP round trip.
+pub(crate) async fn notify(config: &Option<NotifyConfig>, subject: &str, body: &str) {
+    let Some(config) = config.clone() else {
+        return;
+    };
+    let subject = subject.to_string();
+    let body = body.to_string();
+    let result = tokio::task::spawn_blocking(move || send(&config, &subject, &body)).await;
+    if let Err(e) = result.unwrap_or_else(|e| Err(e.into())) {
+        eprintln!("[Info]: Failed to send notification email: {}", e);
+    }
+}
+
+fn send(config: &NotifyConfig, subject: &str, body: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let email = Message::builder()
+        .from(config.username.parse()?)
+        .to(config.to.parse()?)
+        .subject(subject)
+        .body(body.to_string())?;
+    let creds = Credentials::new(config.username.clone(), config.password.clone());
+    // Port 465 expects an implicit-TLS handshake on connect; every other port (587
+    // being the common case) expects a plaintext connection that upgrades via STARTTLS.
+    let builder = if config.port == 465 {
+        SmtpTransport::relay(&config.host)?
+    } else {
+        SmtpTransport::starttls_relay(&config.host)?
+    };
+    let mailer = builder.port(config.port).credentials(creds).build();
+    mailer.send(&email)?;
+    println!("[Info]: Notification email sent");
+    Ok(())
+}
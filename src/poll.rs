@@ -0,0 +1,94 @@
+use buaa_api::{IClassSchedule, Session};
+use tokio::time::Duration;
+
+use crate::get_primitive_time;
+use crate::history::{History, HistoryEntry};
+use crate::notify::{self, NotifyConfig};
+
+/// Open/closed state of a schedule's check-in window, analogous to an
+/// `Available`/`Full`/`Closed` resource-state type.
+#[derive(Debug, PartialEq)]
+pub(crate) enum ScheduleState {
+    /// The check-in window is currently open.
+    Open,
+    /// The teacher has not opened the check-in window yet.
+    NotStarted,
+    /// The check-in window has already closed.
+    Closed,
+}
+
+pub(crate) fn schedule_state(schedule: &IClassSchedule) -> ScheduleState {
+    match schedule.status.as_str() {
+        "进行中" => ScheduleState::Open,
+        "未开始" => ScheduleState::NotStarted,
+        _ => ScheduleState::Closed,
+    }
+}
+
+/// Poll `course`'s schedules every `poll_interval` seconds until one is open for
+/// check-in, then check in. Gives up once `window_minutes` have elapsed since the
+/// call started. Query errors back off exponentially instead of aborting immediately.
+pub(crate) async fn poll_until_open(
+    session: &mut Session,
+    course: &str,
+    user_id: &str,
+    poll_interval: u64,
+    window_minutes: u64,
+    notify_config: &Option<NotifyConfig>,
+    history: &mut History,
+    target_time: &str,
+) {
+    let deadline = get_primitive_time() + time::Duration::minutes(window_minutes as i64);
+    let mut backoff = poll_interval;
+
+    loop {
+        if get_primitive_time() > deadline {
+            println!("[Info]: Polling window elapsed, giving up");
+            return;
+        }
+
+        match session.iclass_query_schedule(course, user_id).await {
+            Ok(schedules) => {
+                backoff = poll_interval;
+                match schedules.iter().find(|s| schedule_state(s) == ScheduleState::Open) {
+                    Some(schedule) => {
+                        let fire_time = get_primitive_time();
+                        let success = match session.iclass_checkin_schedule(&schedule.id, user_id).await {
+                            Ok(_) => {
+                                println!("[Info]: Checkin successfully");
+                                notify::notify(notify_config, "IClass checkin succeeded", &format!(
+                                    "Checked in to course {} at {}", course, fire_time
+                                )).await;
+                                true
+                            },
+                            Err(e) => {
+                                eprintln!("[Info]: Checkin failed: {:?}", e);
+                                notify::notify(notify_config, "IClass checkin failed", &format!(
+                                    "Checkin to course {} failed at {}: {:?}", course, fire_time, e
+                                )).await;
+                                false
+                            },
+                        };
+                        history.record(HistoryEntry {
+                            course: Some(course.to_string()),
+                            schedule: schedule.id.clone(),
+                            target_time: Some(target_time.to_string()),
+                            fire_time: fire_time.to_string(),
+                            success,
+                        });
+                        return;
+                    },
+                    None => {
+                        println!("[Info]: No open schedule yet, retrying in {}s", poll_interval);
+                        tokio::time::sleep(Duration::from_secs(poll_interval)).await;
+                    },
+                }
+            },
+            Err(e) => {
+                eprintln!("[Info]: Query schedule failed: {:?}, retrying in {}s", e, backoff);
+                tokio::time::sleep(Duration::from_secs(backoff)).await;
+                backoff = (backoff * 2).min(60);
+            },
+        }
+    }
+}
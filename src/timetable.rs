@@ -0,0 +1,201 @@
+use std::collections::HashMap;
+
+use buaa_api::IClassCourse;
+use serde::Deserialize;
+use serde_json::json;
+use time::{Date, Month, Time};
+
+use crate::CheckinJob;
+
+/// Credentials for a WebUntis-like external timetable source, used when IClass
+/// itself doesn't expose concrete weekly meeting times for a course.
+pub(crate) struct UntisCredentials {
+    pub(crate) base_url: String,
+    pub(crate) school: String,
+    pub(crate) username: String,
+    pub(crate) password: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct RpcResponse<T> {
+    result: Option<T>,
+    error: Option<RpcError>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct AuthenticateResult {
+    #[serde(rename = "sessionId")]
+    session_id: String,
+}
+
+/// A single timetable period as returned by the external source: `date` is a
+/// `YYYYMMDD` integer and `start_time` an `HHMM` integer.
+#[derive(Debug, Deserialize)]
+pub(crate) struct UntisPeriod {
+    date: i32,
+    #[serde(rename = "startTime")]
+    start_time: i32,
+    su: Vec<UntisSubject>,
+}
+
+#[derive(Debug, Deserialize)]
+struct UntisSubject {
+    name: String,
+}
+
+async fn rpc<T: for<'de> Deserialize<'de>>(
+    client: &reqwest::Client,
+    url: &str,
+    method: &str,
+    params: serde_json::Value,
+) -> Result<T, Box<dyn std::error::Error>> {
+    let body = json!({
+        "id": "buaa-iclass-cli",
+        "method": method,
+        "params": params,
+        "jsonrpc": "2.0",
+    });
+    let response: RpcResponse<T> = client.post(url).json(&body).send().await?.json().await?;
+    match response.result {
+        Some(result) => Ok(result),
+        None => Err(response
+            .error
+            .map(|e| format!("{} ({})", e.message, e.code))
+            .unwrap_or_else(|| "malformed RPC response (no result or error)".to_string())
+            .into()),
+    }
+}
+
+/// Log in to the external timetable source and return a session ID to use for
+/// subsequent requests. The real session is carried by the `Set-Cookie` the server
+/// sends back, so the caller's `client` must keep a cookie jar for this to matter.
+async fn authenticate(client: &reqwest::Client, creds: &UntisCredentials) -> Result<String, Box<dyn std::error::Error>> {
+    let url = format!("{}/WebUntis/jsonrpc.do?school={}", creds.base_url, creds.school);
+    let result: AuthenticateResult = rpc(
+        client,
+        &url,
+        "authenticate",
+        json!({ "user": creds.username, "password": creds.password, "client": "buaa-iclass-cli" }),
+    )
+    .await?;
+    Ok(result.session_id)
+}
+
+/// Fetch every period in the timetable between `start`/`end` (`YYYYMMDD` integers).
+async fn get_timetable(
+    client: &reqwest::Client,
+    creds: &UntisCredentials,
+    session_id: &str,
+    start: i32,
+    end: i32,
+) -> Result<Vec<UntisPeriod>, Box<dyn std::error::Error>> {
+    let url = format!("{}/WebUntis/jsonrpc.do?school={}", creds.base_url, creds.school);
+    rpc(
+        client,
+        &url,
+        "getTimetable",
+        json!({ "sessionId": session_id, "options": { "startDate": start, "endDate": end } }),
+    )
+    .await
+}
+
+/// Decode a `YYYYMMDD` integer into a `Date`, or `None` if it doesn't describe a
+/// real calendar day (the external source is not authenticated by us and may hand
+/// back garbage for a given period).
+pub(crate) fn decode_date(date: i32) -> Option<Date> {
+    let year = date / 10000;
+    let month = (date / 100) % 100;
+    let day = date % 100;
+    let month = Month::try_from(month as u8).ok()?;
+    Date::from_calendar_date(year, month, day as u8).ok()
+}
+
+/// Decode an `HHMM` integer into a `Time`, or `None` if the hour/minute are out of range.
+fn decode_time(time: i32) -> Option<Time> {
+    let hour = time / 100;
+    let minute = time % 100;
+    Time::from_hms(u8::try_from(hour).ok()?, u8::try_from(minute).ok()?, 0).ok()
+}
+
+/// Import a term's timetable from an external WebUntis-like source and turn it into
+/// recurring check-in jobs, matching periods to IClass courses by subject name.
+pub(crate) async fn import_jobs(
+    creds: &UntisCredentials,
+    start: i32,
+    end: i32,
+    courses: &[IClassCourse],
+) -> Result<Vec<CheckinJob>, Box<dyn std::error::Error>> {
+    let client = reqwest::Client::builder().cookie_store(true).build()?;
+    let session_id = authenticate(&client, creds).await?;
+    let periods = get_timetable(&client, creds, &session_id, start, end).await?;
+
+    // Group periods by (matched course, time) to derive one job per weekly slot.
+    let mut slots: HashMap<(String, String), u8> = HashMap::new();
+    for period in periods {
+        let Some(subject) = period.su.first() else {
+            continue;
+        };
+        let Some(course) = courses.iter().find(|c| c.name.contains(&subject.name) || subject.name.contains(&c.name)) else {
+            eprintln!("[Info]: No IClass course matches timetable subject '{}', skipping", subject.name);
+            continue;
+        };
+        let Some(date) = decode_date(period.date) else {
+            eprintln!("[Info]: Period has malformed date '{}', skipping", period.date);
+            continue;
+        };
+        let Some(time) = decode_time(period.start_time) else {
+            eprintln!("[Info]: Period has malformed start time '{}', skipping", period.start_time);
+            continue;
+        };
+        let bit = 1u8 << (date.weekday().number_from_monday() - 1);
+        let key = (course.id.clone(), format!("{:02}{:02}", time.hour(), time.minute()));
+        let mask = slots.entry(key).or_insert(0);
+        *mask |= bit;
+    }
+
+    Ok(slots
+        .into_iter()
+        .map(|((course, time), weekday_mask)| CheckinJob { course, weekday_mask, time })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode_date_parses_valid_yyyymmdd() {
+        let date = decode_date(20240305).unwrap();
+        assert_eq!(date.year(), 2024);
+        assert_eq!(date.month(), Month::March);
+        assert_eq!(date.day(), 5);
+    }
+
+    #[test]
+    fn decode_date_rejects_invalid_month() {
+        assert_eq!(decode_date(20241305), None);
+    }
+
+    #[test]
+    fn decode_date_rejects_garbage() {
+        assert_eq!(decode_date(-1), None);
+    }
+
+    #[test]
+    fn decode_time_parses_valid_hhmm() {
+        let time = decode_time(830).unwrap();
+        assert_eq!(time.hour(), 8);
+        assert_eq!(time.minute(), 30);
+    }
+
+    #[test]
+    fn decode_time_rejects_out_of_range_hour() {
+        assert_eq!(decode_time(2500), None);
+    }
+}